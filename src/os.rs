@@ -1,13 +1,13 @@
 use std::{
-    collections::HashMap,
+    collections::{HashMap, VecDeque},
     default,
     rc::{self, Rc},
     sync::{Arc, Mutex},
 };
 
 use crate::{
-    proc::{PId, Process, ProcessState},
-    scheduler::Scheduler,
+    proc::{PId, Process, ProcSnapshot, ProcessState, Task},
+    scheduler::{SchedCommand, Scheduler},
     timer::hashed_wheel::HashedWheel,
 };
 
@@ -20,12 +20,17 @@ pub struct Os {
     clock: u64,
     waiting_list: HashedWheel<PId>,
     processes: HashMap<PId, Process>,
-    running_process_pid: Option<PId>,
+    running: Vec<Option<PId>>,
+    join_waiters: HashMap<PId, Vec<PId>>,
     scheduler: Arc<Mutex<Box<dyn Scheduler + Send>>>,
 }
 
 impl Os {
-    pub fn new(interval: Option<u64>, scheduler: Arc<Mutex<Box<dyn Scheduler + Send>>>) -> Os {
+    pub fn new(
+        core_count: usize,
+        interval: Option<u64>,
+        scheduler: Arc<Mutex<Box<dyn Scheduler + Send>>>,
+    ) -> Os {
         Os {
             interval: match interval {
                 Some(x) => x,
@@ -34,24 +39,41 @@ impl Os {
             clock: 0,
             waiting_list: HashedWheel::new(),
             processes: HashMap::new(),
-            running_process_pid: None,
+            running: vec![None; core_count.max(1)],
+            join_waiters: HashMap::new(),
             scheduler,
         }
     }
 
+    pub fn core_count(&self) -> usize {
+        self.running.len()
+    }
+
+    pub fn clock(&self) -> u64 {
+        self.clock
+    }
+
+    pub fn interval(&self) -> u64 {
+        self.interval
+    }
+
     pub fn add_proc(&mut self, process: &mut Process) {
         let pid = self.generate_pid();
         process.set_pid(pid);
         self.processes.insert(pid, process.to_owned());
     }
 
-    pub fn get_proc(&mut self, pid: &PId) -> &Process {
-        &self.processes[pid]
+    pub fn get_proc(&self, pid: &PId) -> Option<&Process> {
+        self.processes.get(pid)
+    }
+
+    pub fn get_proc_mut(&mut self, pid: &PId) -> Option<&mut Process> {
+        self.processes.get_mut(pid)
     }
 
-    pub fn current_proc(&self) -> Option<&Process> {
-        self.running_process_pid
-            .and_then(|pid| Some(&self.processes[&pid]))
+    pub fn running_process(&mut self, core_id: usize) -> Option<&mut Process> {
+        let pid = self.running.get(core_id).copied().flatten()?;
+        self.processes.get_mut(&pid)
     }
 
     pub fn run(&mut self) {
@@ -84,20 +106,20 @@ impl Os {
         self.processes.is_empty() || self.processes.iter().all(|(_, v)| v.complete)
     }
 
-    pub fn switch_proc(&mut self, pid: Option<PId>) {
+    pub fn switch_proc(&mut self, core_id: usize, pid: Option<PId>) {
         match pid {
             Some(pid) => {
-                if let Some(cur_pid) = self.running_process_pid {
+                if let Some(cur_pid) = self.running[core_id] {
                     if cur_pid == pid {
                         self.processes.get_mut(&pid).unwrap().state = ProcessState::Waiting;
                     }
-                    if self.processes.contains_key(&pid) {
-                        self.running_process_pid = Some(pid);
-                        self.processes.get_mut(&pid).unwrap().state = ProcessState::Running;
-                    }
+                }
+                if self.processes.contains_key(&pid) {
+                    self.running[core_id] = Some(pid);
+                    self.processes.get_mut(&pid).unwrap().state = ProcessState::Running;
                 }
             }
-            None => self.running_process_pid = None,
+            None => self.running[core_id] = None,
         }
     }
 
@@ -110,11 +132,37 @@ impl Os {
         self.waiting_list.expire_timeout()
     }
 
-    pub fn is_proc_running(&self, pid: PId) -> bool {
-        match self.running_process_pid {
-            Some(id) => id == pid,
-            None => false,
-        }
+    pub fn is_proc_running(&self, core_id: usize, pid: PId) -> bool {
+        self.running[core_id] == Some(pid)
+    }
+
+    pub fn core_of(&self, pid: PId) -> Option<usize> {
+        self.running.iter().position(|&p| p == Some(pid))
+    }
+
+    /// A point-in-time snapshot of every live process, for "what-if"
+    /// experiments and step-through visualization.
+    pub fn inspect(&self) -> Vec<ProcSnapshot> {
+        let scheduler = self.scheduler.lock().expect("lock failed");
+        self.processes
+            .values()
+            .map(|p| ProcSnapshot {
+                pid: p.id,
+                state: p.state,
+                priority: p.priority,
+                queue_level: scheduler.queue_level(p.id),
+                remaining_time: p.remaining_time,
+                wait_time: (self.clock.saturating_sub(p.arrive_time))
+                    .saturating_sub(p.time_have_burst),
+            })
+            .collect()
+    }
+
+    /// Retunes the scheduler mid-simulation (time slices, lottery tickets,
+    /// ...) without rebuilding the `Os`/scheduler pair.
+    pub fn send_command(&self, cmd: SchedCommand) {
+        let mut scheduler = self.scheduler.lock().expect("lock failed");
+        scheduler.reconfigure(cmd);
     }
 
     pub fn complete_proc(&mut self, pid: PId) {
@@ -124,6 +172,67 @@ impl Os {
                 .unwrap()
                 .set_complete(self.clock);
         }
+
+        // A process can be killed or finish early while still parked in
+        // `waiting_list` (e.g. blocked on I/O). Cancel its timeout here so
+        // it can never resurrect later against a reused pid.
+        self.waiting_list.cancel(&pid);
+
+        // Parents joined on `pid` become ready through `waiting_list` on the
+        // next tick: `tick` holds the scheduler lock while this runs.
+        if let Some(parents) = self.join_waiters.remove(&pid) {
+            for parent in parents {
+                self.waiting_list.add_timeout(parent, 0);
+            }
+        }
+    }
+
+    /// `Task::Spawn`: create a child process seeded with `tasks` and hand
+    /// back its freshly generated pid. `parent` is not recorded on the child.
+    pub fn spawn_child(&mut self, parent: PId, tasks: VecDeque<Task>) -> PId {
+        let _ = parent;
+        let mut child = Process::new(0, self.clock, 0);
+        for task in tasks {
+            child.append_task(task);
+        }
+        let pid = self.generate_pid();
+        child.set_pid(pid);
+        // A task list made up entirely of control flow (e.g. a lone
+        // `Task::Join`) never grows `burst_time`/`remaining_time` past 0, so
+        // there's no CPU time to schedule; complete it immediately instead of
+        // letting its first `burst()` underflow `remaining_time`.
+        let all_control_flow = child.burst_time == 0;
+        self.processes.insert(pid, child);
+        if all_control_flow {
+            self.complete_proc(pid);
+        }
+        pid
+    }
+
+    /// `Task::Join`: park `parent` until `child` terminates, freeing up
+    /// whichever core `parent` was running on. If `child` has already
+    /// terminated (and was pruned from `processes`) by the time `parent`
+    /// reaches its `Task::Join`, `complete_proc` already ran and drained
+    /// `join_waiters` for that pid once, so parking here would wait forever;
+    /// `parent` is made ready again on the next tick instead.
+    pub fn block_on_join(&mut self, parent: PId, child: PId) {
+        if let Some(p) = self.processes.get_mut(&parent) {
+            p.state = ProcessState::Waiting;
+        }
+        if let Some(core_id) = self.core_of(parent) {
+            self.running[core_id] = None;
+        }
+
+        if self
+            .processes
+            .get(&child)
+            .map_or(true, |c| c.is_complete())
+        {
+            self.waiting_list.add_timeout(parent, 0);
+            return;
+        }
+
+        self.join_waiters.entry(child).or_default().push(parent);
     }
 
     fn generate_pid(&mut self) -> PId {
@@ -2,10 +2,40 @@ use std::collections::VecDeque;
 
 pub type PId = usize;
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone)]
 pub enum Task {
     IOBound(u64),
     CPUBound(u64),
+    /// Give up the CPU immediately; the process re-enters the ready queue.
+    Yield,
+    /// Fork a child process seeded with `tasks` and keep running.
+    Spawn { tasks: VecDeque<Task> },
+    /// Block until the process identified by this `PId` terminates.
+    Join(PId),
+}
+
+/// What a process did with the time slice it was just given. Replaces the
+/// plain `Option<Task>` `burst` used to return so the scheduler can react to
+/// cooperative control flow (`Yield`/`Spawn`/`Join`), not just CPU/IO bursts.
+#[derive(Debug, Clone)]
+pub enum BurstSignal {
+    Continue(Task),
+    Yield,
+    Spawn(VecDeque<Task>),
+    BlockedOnJoin(PId),
+}
+
+/// A read-only snapshot of a process for runtime introspection, e.g.
+/// `Os::inspect`. Decoupled from `Process` itself so callers can't reach in
+/// and mutate live scheduling state through it.
+#[derive(Debug, Clone)]
+pub struct ProcSnapshot {
+    pub pid: PId,
+    pub state: ProcessState,
+    pub priority: u32,
+    pub queue_level: Option<usize>,
+    pub remaining_time: u64,
+    pub wait_time: u64,
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -22,6 +52,7 @@ pub struct Process {
     pub tasks: VecDeque<Task>,
     pub state: ProcessState,
     pub cpu: u32,
+    pub priority: u32,
 
     pub name: Option<String>,
     pub arrive_time: u64,
@@ -42,6 +73,7 @@ impl Process {
             id: pid,
             state: ProcessState::Runnable,
             cpu: 0,
+            priority: 1,
             tasks: VecDeque::new(),
             arrive_time: t_arrive,
             turnaround_time: None,
@@ -55,10 +87,14 @@ impl Process {
     }
 
     pub fn append_task(&mut self, task: Task) {
-        self.tasks.push_back(task);
-        match task {
-            Task::IOBound(duration) | Task::CPUBound(duration) => self.burst_time += duration,
+        match &task {
+            Task::IOBound(duration) | Task::CPUBound(duration) => {
+                self.burst_time += duration;
+                self.remaining_time += duration;
+            }
+            Task::Yield | Task::Spawn { .. } | Task::Join(_) => {}
         }
+        self.tasks.push_back(task);
     }
 
     pub(crate) fn set_pid(&mut self, pid: PId) {
@@ -71,7 +107,7 @@ impl Process {
         self.turnaround_time = Some(current_time - self.arrive_time);
     }
 
-    pub(crate) fn burst(&mut self, clock: u64) -> Option<Task> {
+    pub(crate) fn burst(&mut self, clock: u64) -> Option<BurstSignal> {
         if (self.time_have_burst == 0) {
             self.response_time = Some(clock - self.arrive_time - 1);
         }
@@ -83,28 +119,39 @@ impl Process {
             return None;
         }
 
-        self.tasks.front_mut().and_then(|task| -> Option<Task> {
-            match task {
-                Task::IOBound(duration) => {
-                    *duration -= 1;
-                    Some(Task::IOBound(*duration))
-                }
-                Task::CPUBound(duration) => {
-                    *duration -= 1;
-                    Some(Task::CPUBound(*duration))
-                }
+        match self.tasks.front_mut() {
+            Some(Task::IOBound(duration)) => {
+                *duration -= 1;
+                Some(BurstSignal::Continue(Task::IOBound(*duration)))
             }
-        })
+            Some(Task::CPUBound(duration)) => {
+                *duration -= 1;
+                Some(BurstSignal::Continue(Task::CPUBound(*duration)))
+            }
+            Some(Task::Yield) => {
+                self.tasks.pop_front();
+                Some(BurstSignal::Yield)
+            }
+            Some(Task::Spawn { .. }) => match self.tasks.pop_front() {
+                Some(Task::Spawn { tasks }) => Some(BurstSignal::Spawn(tasks)),
+                _ => unreachable!(),
+            },
+            Some(Task::Join(child)) => {
+                let child = *child;
+                self.tasks.pop_front();
+                Some(BurstSignal::BlockedOnJoin(child))
+            }
+            None => None,
+        }
     }
 
     pub(crate) fn bump_to_next(&mut self) -> Option<Task> {
-        self.tasks.pop_front().and_then(|task| {
-            match task {
-                Task::IOBound(duration) | Task::CPUBound(duration) => {
-                    self.time_have_burst += duration
-                }
+        self.tasks.pop_front().and_then(|task| match task {
+            Task::IOBound(duration) | Task::CPUBound(duration) => {
+                self.time_have_burst += duration;
+                Some(task)
             }
-            Some(task)
+            Task::Yield | Task::Spawn { .. } | Task::Join(_) => Some(task),
         })
     }
 
@@ -8,88 +8,183 @@ use priority_queue::PriorityQueue;
 
 use crate::{
     os::Os,
-    proc::{PId, Task},
+    proc::{BurstSignal, PId, Task},
 };
 
+/// A live retuning request for a running scheduler, delivered through
+/// `Os::send_command` without rebuilding the `Os`/scheduler pair.
+#[derive(Debug, Clone)]
+pub enum SchedCommand {
+    /// `RoundRobinScheduler`: change the time slice.
+    SetTimeSlice(u64),
+    /// `MLFQScheduler`: change the per-level time slices.
+    SetMlfqTimeSlices([u64; 2]),
+    /// `FairShareScheduler`: change a process's lottery ticket count.
+    SetTickets { pid: PId, tickets: usize },
+}
+
 pub trait Scheduler {
     fn on_process_ready(&mut self, os: &mut Os, pid: PId);
 
-    fn switch_process(&mut self, os: &mut Os);
+    fn switch_process(&mut self, os: &mut Os, core_id: usize);
+
+    /// Retunes scheduler parameters mid-simulation. Schedulers that don't
+    /// recognize a given command simply ignore it.
+    #[allow(unused)]
+    fn reconfigure(&mut self, cmd: SchedCommand) {}
+
+    /// Reports the process's current ready-queue level, for schedulers that
+    /// have the notion of one (e.g. `MLFQScheduler`). `None` otherwise.
+    #[allow(unused)]
+    fn queue_level(&self, pid: PId) -> Option<usize> {
+        None
+    }
 
     fn on_tick(&mut self, os: &mut Os) {
-        while let Some(p) = os.waiting_list.expire_timeout() {
+        while let Some(p) = os.expired_timeout() {
             self.on_process_ready(os, p);
         }
 
-        self.burst_proc(os);
+        for core_id in 0..os.core_count() {
+            self.burst_proc(os, core_id);
+        }
     }
 
-    fn burst_proc(&mut self, os: &mut Os) {
-        let clock = os.clock;
-        if let Some((new_statement, is_completed, pid)) = os
-            .running_process()
-            .map(|process| (process.burst(clock), process.is_complete(), process.id))
-        {
+    fn burst_proc(&mut self, os: &mut Os, core_id: usize) {
+        let clock = os.clock();
+        let snapshot = os
+            .running_process(core_id)
+            .map(|process| (process.burst(clock), process.is_complete(), process.id));
+
+        if let Some((new_statement, is_completed, pid)) = snapshot {
             if let Some(new_statement) = new_statement {
-                self.run_task(os, new_statement, pid);
+                self.run_task(os, new_statement, pid, core_id);
             } else if is_completed {
                 os.complete_proc(pid);
-                if os.is_proc_running(pid) {
-                    self.switch_process(os);
+                if os.is_proc_running(core_id, pid) {
+                    self.switch_process(os, core_id);
                 }
             }
-            self.on_process_burst(os, pid);
+            self.on_process_burst(os, pid, core_id);
         } else {
-            self.switch_process(os);
+            self.switch_process(os, core_id);
         }
     }
 
-    fn run_task(&mut self, os: &mut Os, task: Task, pid: PId) {
-        match task {
-            Task::CPUBound(duration) => self.run_cpu_bound_task(os, duration, pid),
-            Task::IOBound(duration) => self.run_io_bound_task(os, duration, pid),
+    fn run_task(&mut self, os: &mut Os, signal: BurstSignal, pid: PId, core_id: usize) {
+        match signal {
+            BurstSignal::Continue(Task::CPUBound(duration)) => {
+                self.run_cpu_bound_task(os, duration, pid, core_id)
+            }
+            BurstSignal::Continue(Task::IOBound(duration)) => {
+                self.run_io_bound_task(os, duration, pid, core_id)
+            }
+            BurstSignal::Continue(_) => {}
+            BurstSignal::Yield => self.run_yield_task(os, pid, core_id),
+            BurstSignal::Spawn(tasks) => self.run_spawn_task(os, tasks, pid, core_id),
+            BurstSignal::BlockedOnJoin(child) => self.run_join_task(os, child, pid, core_id),
         }
     }
 
     #[allow(unused)]
-    fn run_cpu_bound_task(&mut self, os: &mut Os, duration: u64, pid: PId) {}
-    fn run_io_bound_task(&mut self, os: &mut Os, duration: u64, pid: PId) {
-        let clock = os.clock;
-        let proc = os.get_proc(&pid);
-        if let Some((pid, is_completed)) = proc.map(|process| {
-            if let Some(next_statement) = process.bump_to_next() {
-                (process.id, process.is_complete())
-            } else {
-                (0, false)
-            }
-        }) {
-            if is_completed {
-                os.complete_proc(pid);
-            } else {
-                os.await_proc(pid, duration);
+    fn run_cpu_bound_task(&mut self, os: &mut Os, duration: u64, pid: PId, core_id: usize) {}
+
+    fn run_io_bound_task(&mut self, os: &mut Os, duration: u64, pid: PId, core_id: usize) {
+        if let Some(process) = os.get_proc_mut(&pid) {
+            if process.bump_to_next().is_some() {
+                if process.is_complete() {
+                    os.complete_proc(pid);
+                } else {
+                    os.await_proc(pid, duration);
+                }
             }
         }
-        if os.is_proc_running(pid) {
-            self.switch_process(os);
+        if os.is_proc_running(core_id, pid) {
+            self.switch_process(os, core_id);
+        }
+    }
+
+    /// `Task::Yield`: re-enter the ready queue and give up the core right away.
+    fn run_yield_task(&mut self, os: &mut Os, pid: PId, core_id: usize) {
+        self.on_process_ready(os, pid);
+        self.switch_process(os, core_id);
+    }
+
+    /// `Task::Spawn`: ask `Os` to materialize the child and make it ready;
+    /// the parent keeps running on its core. A child made up entirely of
+    /// control-flow tasks (e.g. a lone `Task::Join`) completes immediately
+    /// inside `spawn_child` rather than being scheduled, so skip readying it.
+    #[allow(unused)]
+    fn run_spawn_task(&mut self, os: &mut Os, tasks: VecDeque<Task>, pid: PId, core_id: usize) {
+        let child = os.spawn_child(pid, tasks);
+        if os.get_proc(&child).map_or(false, |c| !c.is_complete()) {
+            self.on_process_ready(os, child);
+        }
+    }
+
+    /// `Task::Join`: park the parent until the child terminates, then free
+    /// up its core for someone else.
+    fn run_join_task(&mut self, os: &mut Os, child: PId, pid: PId, core_id: usize) {
+        os.block_on_join(pid, child);
+        if os.is_proc_running(core_id, pid) {
+            self.switch_process(os, core_id);
         }
     }
 
     // Used for preemptive
     #[allow(unused)]
-    fn on_process_burst(&mut self, os: &mut Os, pid: PId) {}
+    fn on_process_burst(&mut self, os: &mut Os, pid: PId, core_id: usize) {}
+}
+
+/// Steals work from the most loaded of the other per-core queues, mirroring
+/// the overcommit/work-stealing runtimes: an idle core takes from the back
+/// of the busiest neighbour instead of sitting empty while work piles up
+/// elsewhere.
+fn steal_back<T>(queues: &mut [VecDeque<T>], core_id: usize) -> Option<T> {
+    let victim = queues
+        .iter()
+        .enumerate()
+        .filter(|(i, q)| *i != core_id && !q.is_empty())
+        .max_by_key(|(_, q)| q.len())
+        .map(|(i, _)| i)?;
+    queues[victim].pop_back()
+}
+
+/// Picks the least loaded queue so newly-ready processes start out balanced
+/// across cores instead of always landing on core 0.
+fn least_loaded<T>(queues: &[VecDeque<T>]) -> usize {
+    queues
+        .iter()
+        .enumerate()
+        .min_by_key(|(_, q)| q.len())
+        .map(|(i, _)| i)
+        .unwrap_or(0)
 }
 
 struct FCFSScheduler {
-    ready_queue: VecDeque<PId>,
+    ready_queues: Vec<VecDeque<PId>>,
+}
+
+impl FCFSScheduler {
+    #[allow(unused)]
+    fn new(core_count: usize) -> Self {
+        Self {
+            ready_queues: vec![VecDeque::new(); core_count.max(1)],
+        }
+    }
 }
 
 impl Scheduler for FCFSScheduler {
-    fn on_process_ready(&mut self, os: &mut Os, pid: PId) {
-        self.ready_queue.push_back(pid);
+    fn on_process_ready(&mut self, _os: &mut Os, pid: PId) {
+        let target = least_loaded(&self.ready_queues);
+        self.ready_queues[target].push_back(pid);
     }
 
-    fn switch_process(&mut self, os: &mut Os) {
-        os.switch_proc(self.ready_queue.pop_front());
+    fn switch_process(&mut self, os: &mut Os, core_id: usize) {
+        let pid = self.ready_queues[core_id]
+            .pop_front()
+            .or_else(|| steal_back(&mut self.ready_queues, core_id));
+        os.switch_proc(core_id, pid);
     }
 }
 
@@ -106,9 +201,9 @@ impl Scheduler for SJFScheduler {
         }
     }
 
-    fn switch_process(&mut self, os: &mut Os) {
+    fn switch_process(&mut self, os: &mut Os, core_id: usize) {
         let pid = self.ready_queue.pop();
-        os.switch_proc(pid.and_then(|p| Some(p.0)));
+        os.switch_proc(core_id, pid.and_then(|p| Some(p.0)));
     }
 }
 
@@ -124,12 +219,12 @@ impl Scheduler for STCFScheduler {
         }
     }
 
-    fn switch_process(&mut self, os: &mut Os) {
+    fn switch_process(&mut self, os: &mut Os, core_id: usize) {
         let pid = self.ready_queue.pop();
-        os.switch_proc(pid.and_then(|p| Some(p.0)));
+        os.switch_proc(core_id, pid.and_then(|p| Some(p.0)));
     }
 
-    fn on_process_burst(&mut self, os: &mut Os, pid: PId) {
+    fn on_process_burst(&mut self, os: &mut Os, pid: PId, core_id: usize) {
         let process_remaining_time = os.get_proc(&pid).map(|p| p.remaining_time).unwrap_or(0);
         if self
             .ready_queue
@@ -138,51 +233,107 @@ impl Scheduler for STCFScheduler {
                 top_remaining_time.gt(&&Reverse(process_remaining_time))
             })
         {
-            self.switch_process(os);
+            self.switch_process(os, core_id);
             self.ready_queue.push(pid, Reverse(process_remaining_time));
         }
     }
 }
+
 struct RoundRobinScheduler {
-    ready_queue: VecDeque<PId>,
+    ready_queues: Vec<VecDeque<PId>>,
     used_time_slice_map: HashMap<PId, u64>,
     time_slice: u64,
 }
+
+impl RoundRobinScheduler {
+    #[allow(unused)]
+    fn new(core_count: usize, time_slice: u64) -> Self {
+        Self {
+            ready_queues: vec![VecDeque::new(); core_count.max(1)],
+            used_time_slice_map: HashMap::new(),
+            time_slice,
+        }
+    }
+}
+
 impl Scheduler for RoundRobinScheduler {
-    fn on_process_ready(&mut self, os: &mut Os, pid: PId) {
-        self.ready_queue.push_back(pid);
+    fn on_process_ready(&mut self, _os: &mut Os, pid: PId) {
+        let target = least_loaded(&self.ready_queues);
+        self.ready_queues[target].push_back(pid);
     }
 
-    fn switch_process(&mut self, os: &mut Os) {
-        os.switch_proc(self.ready_queue.pop_front());
+    fn switch_process(&mut self, os: &mut Os, core_id: usize) {
+        let pid = self.ready_queues[core_id]
+            .pop_front()
+            .or_else(|| steal_back(&mut self.ready_queues, core_id));
+        os.switch_proc(core_id, pid);
     }
 
-    fn on_process_burst(&mut self, os: &mut Os, pid: PId) {
+    fn on_process_burst(&mut self, os: &mut Os, pid: PId, core_id: usize) {
         let used_time_slice = self.used_time_slice_map.get(&pid).unwrap_or(&0).clone();
-        if used_time_slice >= self.time_slice && os.is_proc_running(pid) {
-            self.ready_queue.push_back(pid);
+        if used_time_slice >= self.time_slice && os.is_proc_running(core_id, pid) {
+            self.ready_queues[core_id].push_back(pid);
             self.used_time_slice_map.insert(pid, 0);
-            self.switch_process(os);
+            self.switch_process(os, core_id);
         } else {
             self.used_time_slice_map
-                .insert(pid, used_time_slice + os.interval);
+                .insert(pid, used_time_slice + os.interval());
+        }
+    }
+
+    fn reconfigure(&mut self, cmd: SchedCommand) {
+        if let SchedCommand::SetTimeSlice(time_slice) = cmd {
+            self.time_slice = time_slice;
         }
     }
 }
 
+/// Steals from the busiest other core across every priority level, taking
+/// the highest-priority runnable process it finds there.
+fn steal_mlfq(queues: &mut [[IndexSet<PId>; 3]], core_id: usize) -> Option<(PId, usize)> {
+    let victim = queues
+        .iter()
+        .enumerate()
+        .filter(|(i, _)| *i != core_id)
+        .max_by_key(|(_, qs)| qs.iter().map(|q| q.len()).sum::<usize>())
+        .map(|(i, _)| i)?;
+    for (pr, q) in queues[victim].iter_mut().enumerate() {
+        if let Some(pid) = q.pop() {
+            return Some((pid, pr));
+        }
+    }
+    None
+}
+
 struct MLFQScheduler {
-    ready_queues: [IndexSet<PId>; 3],
+    ready_queues: Vec<[IndexSet<PId>; 3]>,
     used_time_slice_map: HashMap<PId, u64>,
-    running_process: Option<(PId, usize)>,
+    running: Vec<Option<(PId, usize)>>,
     time_slices: [u64; 2],
+    /// Priority-boost (aging) interval `S`: every `S` ticks of `os.clock()`,
+    /// every process is moved back to level 0 so CPU-bound jobs that sank to
+    /// the bottom can't starve forever once interactive jobs keep arriving.
+    boost_interval: u64,
 }
 
 impl MLFQScheduler {
-    fn get_priority(&self, pid: PId) -> usize {
-        self.running_process
+    #[allow(unused)]
+    fn new(core_count: usize, time_slices: [u64; 2], boost_interval: u64) -> Self {
+        let core_count = core_count.max(1);
+        Self {
+            ready_queues: (0..core_count).map(|_| Default::default()).collect(),
+            used_time_slice_map: HashMap::new(),
+            running: vec![None; core_count],
+            time_slices,
+            boost_interval,
+        }
+    }
+
+    fn get_priority(&self, core_id: usize, pid: PId) -> usize {
+        self.running[core_id]
             .and_then(|(p, priority)| (pid == p).then(|| priority))
             .unwrap_or_else(|| {
-                self.ready_queues
+                self.ready_queues[core_id]
                     .iter()
                     .enumerate()
                     .find_map(|(pr, q)| q.get(&pid).and(Some(pr)))
@@ -190,71 +341,164 @@ impl MLFQScheduler {
             })
     }
 
-    fn level_down(&mut self, pid: PId) {
-        let pr = self.get_priority(pid);
-        if pr >= self.ready_queues.len() - 1 {
+    fn level_down(&mut self, core_id: usize, pid: PId) {
+        let pr = self.get_priority(core_id, pid);
+        if pr >= self.ready_queues[core_id].len() - 1 {
             return;
         }
-        self.ready_queues[pr].remove(&pid);
-        self.ready_queues[pr + 1].insert(pid);
+        self.ready_queues[core_id][pr].remove(&pid);
+        self.ready_queues[core_id][pr + 1].insert(pid);
     }
 
-    fn is_proc_running(&self, pid: PId) -> bool {
-        self.running_process.map_or(false, |(id, _)| id == pid)
+    fn is_proc_running(&self, core_id: usize, pid: PId) -> bool {
+        self.running[core_id].map_or(false, |(id, _)| id == pid)
+    }
+
+    /// Aging sweep: drain every non-zero level into level 0 on every core,
+    /// preserving each level's FIFO order in the merge, and reset the
+    /// running process on every core back to level 0 too.
+    fn boost(&mut self) {
+        for core_id in 0..self.ready_queues.len() {
+            let last_priority = self.ready_queues[core_id].len() - 1;
+            for level in 1..=last_priority {
+                let starved: Vec<PId> = self.ready_queues[core_id][level].drain(..).collect();
+                for pid in starved {
+                    self.used_time_slice_map.remove(&pid);
+                    self.ready_queues[core_id][0].insert(pid);
+                }
+            }
+
+            if let Some((pid, _)) = self.running[core_id] {
+                self.used_time_slice_map.remove(&pid);
+                self.running[core_id] = Some((pid, 0));
+            }
+        }
     }
 }
 
 impl Scheduler for MLFQScheduler {
-    fn on_process_ready(&mut self, os: &mut Os, pid: PId) {
-        self.ready_queues[0].insert(pid);
+    fn on_process_ready(&mut self, _os: &mut Os, pid: PId) {
+        let target = self
+            .ready_queues
+            .iter()
+            .enumerate()
+            .min_by_key(|(_, qs)| qs.iter().map(|q| q.len()).sum::<usize>())
+            .map(|(i, _)| i)
+            .unwrap_or(0);
+        self.ready_queues[target][0].insert(pid);
     }
 
-    fn switch_process(&mut self, os: &mut Os) {
-        if let Some((pid, pr)) = self
-            .ready_queues
+    fn on_tick(&mut self, os: &mut Os) {
+        while let Some(p) = os.expired_timeout() {
+            self.on_process_ready(os, p);
+        }
+
+        for core_id in 0..os.core_count() {
+            self.burst_proc(os, core_id);
+        }
+
+        if self.boost_interval > 0 && os.clock() % self.boost_interval == 0 {
+            self.boost();
+        }
+    }
+
+    /// Re-enters the ready queue at the *current* level rather than the
+    /// generic (level-0) `on_process_ready`. `used_time_slice_map` isn't
+    /// touched here either way: `burst_proc` still calls
+    /// `on_process_burst(os, pid, core_id)` for `pid` right after this
+    /// returns, and since `pid` is no longer the running process on
+    /// `core_id` (unless nothing else was ready and it was picked straight
+    /// back) that call keeps accumulating its used time slice instead of
+    /// resetting it — a process that yields right before its slice expires
+    /// still accumulates towards demotion next time it runs, instead of
+    /// dodging it by giving up the CPU early.
+    fn run_yield_task(&mut self, os: &mut Os, pid: PId, core_id: usize) {
+        let priority = self.get_priority(core_id, pid);
+        self.switch_process(os, core_id);
+        self.ready_queues[core_id][priority].insert(pid);
+        if self.running[core_id].is_none() {
+            self.switch_process(os, core_id);
+        }
+    }
+
+    fn switch_process(&mut self, os: &mut Os, core_id: usize) {
+        let next = self.ready_queues[core_id]
             .iter_mut()
             .enumerate()
             .find_map(|(pri, q)| q.pop().map(|pid| (pid, pri)))
-        {
-            self.running_process = Some((pid, pr));
-            os.switch_proc(Some(pid));
+            .or_else(|| steal_mlfq(&mut self.ready_queues, core_id));
+
+        if let Some((pid, pr)) = next {
+            self.running[core_id] = Some((pid, pr));
+            os.switch_proc(core_id, Some(pid));
         } else {
-            self.running_process = None;
-            os.switch_proc(None);
+            self.running[core_id] = None;
+            os.switch_proc(core_id, None);
         }
     }
 
-    fn on_process_burst(&mut self, os: &mut Os, pid: PId) {
-        let priority = self.get_priority(pid);
-        let last_priority = self.ready_queues.len() - 1;
+    fn on_process_burst(&mut self, os: &mut Os, pid: PId, core_id: usize) {
+        let priority = self.get_priority(core_id, pid);
+        let last_priority = self.ready_queues[core_id].len() - 1;
         if priority >= last_priority {
-            if self.ready_queues[0..last_priority]
+            if self.ready_queues[core_id][0..last_priority]
                 .iter()
                 .any(|q| !q.is_empty())
             {
-                self.ready_queues[last_priority].insert(pid);
-                self.switch_process(os);
+                self.ready_queues[core_id][last_priority].insert(pid);
+                self.switch_process(os, core_id);
             }
         } else {
             let used_time_slice = self.used_time_slice_map.get(&pid).copied().unwrap_or(0);
-            if used_time_slice >= self.time_slices[priority] && self.is_proc_running(pid) {
-                self.level_down(pid);
+            if used_time_slice >= self.time_slices[priority] && self.is_proc_running(core_id, pid)
+            {
+                self.level_down(core_id, pid);
                 self.used_time_slice_map.insert(pid, 0);
-                self.switch_process(os);
+                self.switch_process(os, core_id);
             } else {
                 self.used_time_slice_map
-                    .insert(pid, used_time_slice + os.interval);
+                    .insert(pid, used_time_slice + os.interval());
             }
         }
     }
+
+    fn reconfigure(&mut self, cmd: SchedCommand) {
+        if let SchedCommand::SetMlfqTimeSlices(time_slices) = cmd {
+            self.time_slices = time_slices;
+        }
+    }
+
+    fn queue_level(&self, pid: PId) -> Option<usize> {
+        (0..self.running.len()).find_map(|core_id| {
+            if self.is_proc_running(core_id, pid) {
+                self.running[core_id].map(|(_, pr)| pr)
+            } else {
+                self.ready_queues[core_id]
+                    .iter()
+                    .enumerate()
+                    .find_map(|(pr, q)| q.get(&pid).and(Some(pr)))
+            }
+        })
+    }
 }
 
 struct FairShareScheduler {
     total_ticket: usize,
-    next_pid: Option<PId>,
+    next_pid: Vec<Option<PId>>,
     process_ticket: HashMap<PId, usize>,
 }
 
+impl FairShareScheduler {
+    #[allow(unused)]
+    fn new(core_count: usize) -> Self {
+        Self {
+            total_ticket: 0,
+            next_pid: vec![None; core_count.max(1)],
+            process_ticket: HashMap::new(),
+        }
+    }
+}
+
 impl Scheduler for FairShareScheduler {
     fn on_process_ready(&mut self, os: &mut Os, pid: PId) {
         let ticket = os
@@ -266,11 +510,11 @@ impl Scheduler for FairShareScheduler {
         self.process_ticket.insert(pid, ticket);
     }
 
-    fn switch_process(&mut self, os: &mut Os) {
-        os.switch_proc(self.next_pid);
+    fn switch_process(&mut self, os: &mut Os, core_id: usize) {
+        os.switch_proc(core_id, self.next_pid[core_id]);
     }
 
-    fn on_process_burst(&mut self, os: &mut Os, pid: PId) {
+    fn on_process_burst(&mut self, os: &mut Os, pid: PId, core_id: usize) {
         self.process_ticket.retain(|p, _| {
             os.get_proc(p)
                 .and_then(|proc| Some(!proc.is_complete()))
@@ -278,8 +522,8 @@ impl Scheduler for FairShareScheduler {
         });
         self.total_ticket = self.process_ticket.values().sum();
         if self.process_ticket.len() == 0 {
-            self.next_pid = None;
-            self.switch_process(os);
+            self.next_pid[core_id] = None;
+            self.switch_process(os, core_id);
             return;
         }
 
@@ -289,9 +533,16 @@ impl Scheduler for FairShareScheduler {
             if winner > 0 {
                 continue;
             }
-            self.next_pid = Some(*p);
-            self.switch_process(os);
+            self.next_pid[core_id] = Some(*p);
+            self.switch_process(os, core_id);
             return;
         }
     }
+
+    fn reconfigure(&mut self, cmd: SchedCommand) {
+        if let SchedCommand::SetTickets { pid, tickets } = cmd {
+            let old = self.process_ticket.insert(pid, tickets).unwrap_or(0);
+            self.total_ticket = self.total_ticket + tickets - old;
+        }
+    }
 }
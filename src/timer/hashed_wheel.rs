@@ -1,110 +1,248 @@
-use core::time;
-use std::collections::{linked_list, LinkedList};
+use std::{collections::HashMap, collections::VecDeque, hash::Hash};
 
+/// Number of cascaded levels, level 0 included. Level `k` covers `wheel_size^k`
+/// ticks per slot, so with the default `wheel_size` of 8 the four levels
+/// together cover `8^4 = 4096` ticks before anything needs a residual round
+/// count.
+const LEVELS: usize = 4;
+
+/// An intrusive doubly-linked list node: `prev`/`next` store neighbouring *keys* rather than pointers, so a node
+/// can be unlinked from its bucket in O(1) via a single `table` lookup
+/// instead of scanning the bucket — this is what lets `cancel` be O(1) even
+/// though timeouts are spread across cascaded levels.
 #[derive(Debug, Clone)]
-pub struct HashedWheelTimeout<T>
-where
-    T: Clone,
-{
-    item: T,
-    round: isize,
+struct Node<T> {
+    level: usize,
+    slot: usize,
+    deadline_tick: u64,
+    /// Extra full rotations of the top level to wait out before the entry
+    /// is actually due. Only nonzero for deadlines beyond total capacity.
+    round: u64,
+    prev: Option<T>,
+    next: Option<T>,
 }
 
-impl<T> HashedWheelTimeout<T>
-where
-    T: Clone,
-{
-    fn new(item: T, round: isize) -> Self {
-        Self { item, round }
-    }
-
-    fn tick_a_round(&mut self) {
-        self.round -= 1;
-    }
-}
 #[derive(Debug, Clone)]
-pub struct HashedWheelBucket<T>
-where
-    T: Clone,
-{
-    timeouts: LinkedList<HashedWheelTimeout<T>>,
+struct Bucket<T> {
+    head: Option<T>,
+    tail: Option<T>,
 }
 
-impl<T> HashedWheelBucket<T>
-where
-    T: Clone,
-{
-    fn new() -> Self {
+impl<T> Bucket<T> {
+    fn empty() -> Self {
         Self {
-            timeouts: LinkedList::new(),
-        }
-    }
-
-    fn tick(&mut self) {
-        self.timeouts.iter_mut().for_each(|t| t.tick_a_round());
-    }
-
-    fn empty(&self) -> bool {
-        self.timeouts.is_empty()
-    }
-
-    fn add_timeout(&mut self, timeout: HashedWheelTimeout<T>) {
-        self.timeouts.push_back(timeout);
-    }
-
-    fn expired_timeout(&mut self) -> Option<T> {
-        if let Some(expire) = self.timeouts.front().and_then(|t| Some(t.round <= 0)) {
-            self.timeouts.pop_front().and_then(|t| Some(t.item))
-        } else {
-            None
+            head: None,
+            tail: None,
         }
     }
 }
+
+/// A cascading hierarchical timing wheel. Level 0 holds timeouts due within
+/// the next `wheel_size` ticks at slot granularity 1; level `k` holds ones
+/// due within `wheel_size^(k+1)` ticks at granularity `wheel_size^k`. On
+/// every `tick`, level 0 advances one slot and, whenever a higher level
+/// completes a full rotation, its newly-current slot is cascaded down into
+/// lower levels with a recomputed (smaller) remaining delay. This keeps
+/// `add_timeout` and `tick` amortized O(1) regardless of how many timeouts
+/// are outstanding, unlike a flat wheel that re-touches every timeout in
+/// every bucket on every tick. Every bucket is itself an intrusive linked
+/// list indexed through `table`, so `cancel` is also O(1) regardless of
+/// which level a timeout currently lives on.
 #[derive(Debug)]
 pub struct HashedWheel<T>
 where
-    T: Clone,
+    T: Eq + Hash + Clone,
 {
-    buckets: Vec<HashedWheelBucket<T>>,
-    pub current_tick: isize,
-    pub wheel_size: usize,
-    pub resolution: usize,
+    levels: Vec<Vec<Bucket<T>>>,
+    table: HashMap<T, Node<T>>,
+    wheel_size: usize,
+    bits: u32,
+    current_tick: u64,
+    due: VecDeque<T>,
 }
 
 impl<T> HashedWheel<T>
 where
-    T: Clone,
+    T: Eq + Hash + Clone,
 {
     pub fn new() -> Self {
         Self::with_size_and_resolution(8, 1)
     }
 
-    pub fn with_size_and_resolution(wheel_size: usize, resolution: usize) -> Self {
-        let buckets = vec![HashedWheelBucket::<T>::new(); wheel_size];
+    pub fn with_size_and_resolution(wheel_size: usize, _resolution: usize) -> Self {
+        let wheel_size = wheel_size.next_power_of_two().max(2);
+        let bits = wheel_size.trailing_zeros();
+        let levels = (0..LEVELS)
+            .map(|_| (0..wheel_size).map(|_| Bucket::empty()).collect())
+            .collect();
         Self {
-            buckets,
-            current_tick: -1,
+            levels,
+            table: HashMap::new(),
             wheel_size,
-            resolution,
+            bits,
+            current_tick: 0,
+            due: VecDeque::new(),
         }
     }
 
     pub fn empty(&self) -> bool {
-        self.buckets.iter().all(|bucket| bucket.empty())
+        self.due.is_empty() && self.table.is_empty()
     }
 
-    pub fn tick(&mut self) {
-        self.current_tick += self.resolution as isize;
-        self.buckets.iter_mut().for_each(|bucket| bucket.tick());
+    fn span(&self, level: usize) -> u64 {
+        (self.wheel_size as u64).pow(level as u32)
     }
 
-    pub fn add_timeout(&mut self, value: T, deadline: isize) {
-        let round = deadline / self.wheel_size as isize + 1;
-        let timeout = HashedWheelTimeout::<T> { item: value, round };
-        self.buckets[deadline as usize % self.wheel_size].add_timeout(timeout);
+    fn capacity(&self) -> u64 {
+        self.span(LEVELS - 1) * self.wheel_size as u64
+    }
+
+    fn slot(&self, level: usize, deadline_tick: u64) -> usize {
+        ((deadline_tick >> (level as u32 * self.bits)) as usize) % self.wheel_size
+    }
+
+    fn level_for(&self, delay: u64) -> usize {
+        (0..LEVELS)
+            .find(|&level| delay < self.span(level) * self.wheel_size as u64)
+            .unwrap_or(LEVELS - 1)
+    }
+
+    /// Links `key` onto the tail of `(level, slot)`'s intrusive list.
+    fn link(&mut self, key: T, level: usize, slot: usize, deadline_tick: u64, round: u64) {
+        let prev = self.levels[level][slot].tail.clone();
+        match &prev {
+            Some(prev_key) => {
+                if let Some(prev_node) = self.table.get_mut(prev_key) {
+                    prev_node.next = Some(key.clone());
+                }
+            }
+            None => self.levels[level][slot].head = Some(key.clone()),
+        }
+        self.levels[level][slot].tail = Some(key.clone());
+        self.table.insert(
+            key,
+            Node {
+                level,
+                slot,
+                deadline_tick,
+                round,
+                prev,
+                next: None,
+            },
+        );
+    }
+
+    /// Unlinks `key`'s node from whichever bucket it currently lives on and
+    /// removes it from `table`, in O(1) — no bucket is scanned.
+    fn unlink(&mut self, key: &T) -> Option<Node<T>> {
+        let node = self.table.remove(key)?;
+
+        match &node.prev {
+            Some(prev_key) => {
+                if let Some(prev_node) = self.table.get_mut(prev_key) {
+                    prev_node.next = node.next.clone();
+                }
+            }
+            None => self.levels[node.level][node.slot].head = node.next.clone(),
+        }
+        match &node.next {
+            Some(next_key) => {
+                if let Some(next_node) = self.table.get_mut(next_key) {
+                    next_node.prev = node.prev.clone();
+                }
+            }
+            None => self.levels[node.level][node.slot].tail = node.prev.clone(),
+        }
+
+        Some(node)
+    }
+
+    /// Places `key` so it eventually fires at the true `deadline_tick`,
+    /// cascading through levels (and, for deadlines beyond `capacity()`,
+    /// through extra top-level rotations counted by `round`) as `tick`
+    /// advances. Crucially, `deadline_tick` itself is never clamped or
+    /// truncated: `slot`/`level_for` are periodic in it (period `capacity()`
+    /// at the top level), so reusing the exact, untouched `deadline_tick` on
+    /// every re-placement — including the ones `tick`'s cascade loop performs
+    /// once `round` counts down to 0 — is what keeps the real remaining
+    /// delay from ever being lost to a clamped placeholder.
+    fn place(&mut self, key: T, deadline_tick: u64) {
+        let delay = deadline_tick.saturating_sub(self.current_tick);
+        let round = if delay >= self.capacity() {
+            (delay - (self.capacity() - 1)) / self.capacity()
+        } else {
+            0
+        };
+        let level = self.level_for(delay);
+        let slot = self.slot(level, deadline_tick);
+        self.link(key, level, slot, deadline_tick, round);
+    }
+
+    /// `deadline` is how many ticks from now the timeout should fire;
+    /// `deadline <= 0` always fires on the very next tick rather than being
+    /// (mis)read as already due. Replaces any timeout already pending for
+    /// `key`.
+    pub fn add_timeout(&mut self, key: T, deadline: isize) {
+        self.cancel(&key);
+        let delay = deadline.max(1) as u64;
+        let deadline_tick = self.current_tick + delay;
+        self.place(key, deadline_tick);
+    }
+
+    /// Cancels `key`'s pending timeout, if any, in O(1) regardless of which
+    /// level it currently lives on. Returns whether a timeout was actually
+    /// cancelled.
+    pub fn cancel(&mut self, key: &T) -> bool {
+        self.unlink(key).is_some()
+    }
+
+    /// Advances the wheel by one tick, cascading any level that just
+    /// completed a full rotation into the levels below it, and collects
+    /// every timeout newly due this tick for `expire_timeout` to drain.
+    pub fn tick(&mut self) {
+        self.current_tick += 1;
+
+        for level in 1..LEVELS {
+            let span = self.span(level);
+            if self.current_tick % span != 0 {
+                break;
+            }
+            let cascade_slot = (self.current_tick / span) as usize % self.wheel_size;
+
+            let mut keys = Vec::new();
+            let mut cursor = self.levels[level][cascade_slot].head.clone();
+            while let Some(key) = cursor {
+                cursor = self.table.get(&key).and_then(|node| node.next.clone());
+                keys.push(key);
+            }
+
+            for key in keys {
+                let node = match self.unlink(&key) {
+                    Some(node) => node,
+                    None => continue,
+                };
+                if node.round > 0 {
+                    self.link(key, level, cascade_slot, node.deadline_tick, node.round - 1);
+                } else {
+                    self.place(key, node.deadline_tick);
+                }
+            }
+        }
+
+        let slot0 = (self.current_tick as usize) % self.wheel_size;
+        let mut keys = Vec::new();
+        let mut cursor = self.levels[0][slot0].head.clone();
+        while let Some(key) = cursor {
+            cursor = self.table.get(&key).and_then(|node| node.next.clone());
+            keys.push(key);
+        }
+        for key in keys {
+            self.unlink(&key);
+            self.due.push_back(key);
+        }
     }
 
     pub fn expire_timeout(&mut self) -> Option<T> {
-        self.buckets[self.current_tick as usize % self.wheel_size].expired_timeout()
+        self.due.pop_front()
     }
 }